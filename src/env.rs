@@ -11,18 +11,37 @@
 
 // TODO: Update the docs
 
-pub use std::env::{
-    self, args, args_os, current_dir, current_exe, join_paths, set_current_dir, split_paths,
-    temp_dir,
-};
-use std::ffi::{OsStr, OsString};
+#[cfg(feature = "std")]
+pub use std::env::{self, args, args_os, current_exe, join_paths, split_paths, temp_dir};
 
+#[cfg(feature = "std")]
+mod command;
+#[cfg(feature = "std")]
+mod cwd;
 mod imp;
+#[cfg(all(unix, feature = "std"))]
+mod raw;
+#[cfg(feature = "std")]
+mod scope;
 
+#[cfg(feature = "std")]
+pub use command::*;
+#[cfg(feature = "std")]
+pub use cwd::*;
 pub use imp::*;
+#[cfg(all(unix, feature = "std"))]
+pub use raw::*;
+#[cfg(feature = "std")]
+pub use scope::*;
 
 use crate::UniversalLock;
 
+// `ToOwned` is part of the `std` prelude, but not the `core` prelude; under
+// `no_std` it's only reachable through `alloc`, so pull it in explicitly for
+// the `to_owned()` calls below.
+#[cfg(not(feature = "std"))]
+use alloc::borrow::ToOwned;
+
 /// Returns an iterator of (variable, value) pairs of OS strings, for all the
 /// environment variables of the current process.
 ///
@@ -51,7 +70,7 @@ use crate::UniversalLock;
 #[must_use]
 pub fn vars_os() -> VarsOs {
     VarsOs {
-        inner: ENV_MAP.u_lock().unwrap().clone().into_iter(),
+        inner: ENV_MAP.u_read().unwrap().clone().into_iter(),
     }
 }
 
@@ -83,6 +102,7 @@ pub fn vars_os() -> VarsOs {
 /// ```
 ///
 /// [`env::vars_os()`]: vars_os
+#[cfg(feature = "std")]
 #[must_use]
 pub fn vars() -> Vars {
     Vars { inner: vars_os() }
@@ -116,12 +136,12 @@ pub fn vars() -> Vars {
 /// If expecting a delimited variable (such as `PATH`), [`split_paths`]
 /// can be used to separate items.
 #[must_use]
-pub fn var_os<K: AsRef<OsStr>>(key: K) -> Option<OsString> {
+pub fn var_os<K: AsRef<EnvStr>>(key: K) -> Option<EnvString> {
     ENV_MAP
-        .u_lock()
+        .u_read()
         .unwrap()
         .get(key.as_ref())
-        .map(std::borrow::ToOwned::to_owned)
+        .map(ToOwned::to_owned)
 }
 
 /// Fetches the environment variable `key` from the current process.
@@ -150,7 +170,8 @@ pub fn var_os<K: AsRef<OsStr>>(key: K) -> Option<OsString> {
 ///     Err(e) => println!("couldn't interpret {key}: {e}"),
 /// }
 /// ```
-pub fn var<K: AsRef<OsStr>>(key: K) -> Result<String, VarError> {
+#[cfg(feature = "std")]
+pub fn var<K: AsRef<EnvStr>>(key: K) -> Result<String, VarError> {
     match var_os(key) {
         Some(v) => Ok(v.into_string().map_err(VarError::NotUnicode)?),
         None => Err(VarError::NotPresent),
@@ -174,8 +195,14 @@ pub fn var<K: AsRef<OsStr>>(key: K) -> Result<String, VarError> {
 /// env::remove_var(key);
 /// assert!(env::var(key).is_err());
 /// ```
-pub fn remove_var<K: AsRef<OsStr>>(key: K) {
-    ENV_MAP.u_lock().unwrap().remove(key.as_ref());
+pub fn remove_var<K: AsRef<EnvStr>>(key: K) {
+    let key = key.as_ref();
+    let previous = ENV_MAP.u_write().unwrap().remove(key);
+
+    #[cfg(feature = "std")]
+    scope::record_mutation(key, previous.as_deref());
+    #[cfg(not(feature = "std"))]
+    drop(previous);
 }
 
 /// Sets the environment variable `key` to the value `value` for the currently running
@@ -193,11 +220,17 @@ pub fn remove_var<K: AsRef<OsStr>>(key: K) {
 /// env::set_var(key, "VALUE");
 /// assert_eq!(env::var(key), Ok("VALUE".to_string()));
 /// ```
-pub fn set_var<K: AsRef<OsStr>, V: AsRef<OsStr>>(key: K, value: V) {
-    ENV_MAP
-        .u_lock()
+pub fn set_var<K: AsRef<EnvStr>, V: AsRef<EnvStr>>(key: K, value: V) {
+    let key = key.as_ref();
+    let previous = ENV_MAP
+        .u_write()
         .unwrap()
-        .insert(key.as_ref().to_owned(), value.as_ref().to_owned());
+        .insert(key.to_owned(), value.as_ref().to_owned());
+
+    #[cfg(feature = "std")]
+    scope::record_mutation(key, previous.as_deref());
+    #[cfg(not(feature = "std"))]
+    drop(previous);
 }
 
 #[cfg(feature = "std")]
@@ -215,7 +248,7 @@ pub fn set_var<K: AsRef<OsStr>, V: AsRef<OsStr>>(key: K, value: V) {
 /// env::fill(env.into_iter());
 /// assert_eq!(env::var("KEY"), Ok("VALUE".to_string()));
 /// ```
-pub fn fill<T: Iterator<Item = (A, B)>, A: AsRef<OsStr>, B: AsRef<OsStr>>(env: T) {
+pub fn fill<T: Iterator<Item = (A, B)>, A: AsRef<EnvStr>, B: AsRef<EnvStr>>(env: T) {
     for (key, value) in env {
         set_var(key, value);
     }
@@ -257,6 +290,6 @@ mod tests {
         set_var("KEY", "VALUE");
         assert_eq!(var("KEY"), Ok("VALUE".to_string()));
 
-        assert_eq!(var_os("KEY"), Some(OsString::from("VALUE")));
+        assert_eq!(var_os("KEY"), Some(EnvString::from("VALUE")));
     }
 }
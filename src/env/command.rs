@@ -0,0 +1,71 @@
+use std::process::Command;
+
+use crate::UniversalLock;
+
+use super::ENV_MAP;
+
+/// Applies the managed `safenv` environment to a [`std::process::Command`].
+///
+/// Takes a snapshot of the current `safenv` environment and replaces the
+/// command's environment with it, via [`Command::env_clear`] followed by
+/// [`Command::envs`], so the spawned child sees exactly the `safenv` view
+/// instead of inheriting the real OS process environment.
+///
+/// # Panics
+/// If the environment lock is poisoned, this function will panic.
+///
+/// # Examples
+///
+/// ```
+/// use std::process::Command;
+///
+/// use safenv::env;
+///
+/// env::set_var("GREETING", "hello");
+///
+/// let mut cmd = Command::new("env");
+/// env::apply_to_command(&mut cmd);
+/// ```
+pub fn apply_to_command(cmd: &mut Command) -> &mut Command {
+    let snapshot = ENV_MAP.u_read().unwrap().clone();
+
+    cmd.env_clear();
+    cmd.envs(snapshot)
+}
+
+/// Extension trait for spawning [`std::process::Command`]s with the managed
+/// `safenv` environment.
+pub trait CommandExt {
+    /// Replaces this command's environment with a snapshot of the `safenv`
+    /// environment. See [`apply_to_command`] for details.
+    fn safenv(&mut self) -> &mut Self;
+}
+
+impl CommandExt for Command {
+    fn safenv(&mut self) -> &mut Self {
+        apply_to_command(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+    use std::process::Command;
+
+    use super::CommandExt;
+    use crate::env;
+
+    #[test]
+    fn safenv_clears_and_replaces_the_command_environment() {
+        env::set_var("COMMAND_TEST_KEY", "COMMAND_TEST_VALUE");
+
+        let mut cmd = Command::new("env");
+        cmd.env("UNMANAGED", "should be cleared");
+        cmd.safenv();
+
+        let envs: Vec<_> = cmd.get_envs().collect();
+        assert!(envs.iter().any(|&(k, v)| k == OsStr::new("COMMAND_TEST_KEY")
+            && v == Some(OsStr::new("COMMAND_TEST_VALUE"))));
+        assert!(!envs.iter().any(|&(k, _)| k == OsStr::new("UNMANAGED")));
+    }
+}
@@ -0,0 +1,91 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::scope;
+
+static CURRENT_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Seeds `guard` from the real process working directory on first access,
+/// returning the (now guaranteed present) tracked value either way.
+fn ensure_init(guard: &mut Option<PathBuf>) -> io::Result<PathBuf> {
+    if let Some(path) = guard.as_ref() {
+        return Ok(path.clone());
+    }
+
+    let path = std::env::current_dir()?;
+    *guard = Some(path.clone());
+    Ok(path)
+}
+
+/// Restores the tracked working directory to `path`, bypassing the usual
+/// [`scope`] bookkeeping. Used by [`EnvGuard`] to undo a scoped change.
+///
+/// [`EnvGuard`]: super::EnvGuard
+pub(crate) fn restore(path: PathBuf) {
+    *CURRENT_DIR.lock().unwrap() = Some(path);
+}
+
+/// Returns the `safenv`-managed current working directory.
+///
+/// Unlike [`std::env::current_dir`], this does not read the real process
+/// working directory on every call: it is seeded from it once, on first
+/// access, and from then on reflects only the changes made through
+/// [`set_current_dir`]. Changes made through [`set_current_dir`] are undone
+/// by [`scope`]'s restore-on-drop guard, without racing on the real process
+/// directory. The tracked directory is not part of the [`vars_os`]
+/// snapshot, which holds environment variables only.
+///
+/// # Errors
+/// Returns an error if the tracked directory has not yet been accessed and
+/// the real process working directory cannot be determined.
+///
+/// # Panics
+/// If the current directory lock is poisoned, this function will panic.
+///
+/// [`vars_os`]: super::vars_os
+pub fn current_dir() -> io::Result<PathBuf> {
+    let mut guard = CURRENT_DIR.lock().unwrap();
+    ensure_init(&mut guard)
+}
+
+/// Sets the `safenv`-managed current working directory to `path`.
+///
+/// This updates only the internal `safenv` state; it does not call
+/// `chdir` and has no effect on the real process working directory seen by
+/// other libraries or by child processes. [`apply_to_command`] carries over
+/// the environment only, not this directory.
+///
+/// [`apply_to_command`]: super::apply_to_command
+///
+/// # Errors
+/// Returns an error if the tracked directory has not yet been accessed and
+/// the real process working directory cannot be determined.
+///
+/// # Panics
+/// If the current directory lock is poisoned, this function will panic.
+pub fn set_current_dir<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let mut guard = CURRENT_DIR.lock().unwrap();
+    let previous = ensure_init(&mut guard)?;
+    *guard = Some(path.as_ref().to_path_buf());
+    drop(guard);
+
+    scope::record_cwd_mutation(&previous);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current_dir, set_current_dir};
+
+    #[test]
+    fn set_current_dir_updates_the_tracked_value() {
+        let before = current_dir().unwrap();
+        set_current_dir("/").unwrap();
+        assert_eq!(current_dir().unwrap(), std::path::Path::new("/"));
+
+        // Restore it so this doesn't leak into other tests that read
+        // `current_dir()`.
+        set_current_dir(before).unwrap();
+    }
+}
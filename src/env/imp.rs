@@ -1,15 +1,72 @@
 use core::fmt;
-use std::{collections::BTreeMap, error::Error, ffi::OsString};
 
-#[cfg(feature = "parking_lot")]
-use parking_lot::Mutex;
+#[cfg(feature = "std")]
+use std::error::Error;
 
-#[cfg(not(feature = "parking_lot"))]
-use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
 
-pub(crate) type EnvMap = BTreeMap<OsString, OsString>;
-
-pub(crate) static ENV_MAP: Mutex<EnvMap> = Mutex::new(BTreeMap::new());
+/// The owned string type used for environment variable keys and values.
+///
+/// This is [`std::ffi::OsString`] when the `std` feature is enabled.
+#[cfg(feature = "std")]
+pub type EnvString = std::ffi::OsString;
+/// The owned string type used for environment variable keys and values.
+///
+/// This falls back to [`alloc::string::String`] under `no_std`, where there
+/// is no platform string type to borrow.
+#[cfg(not(feature = "std"))]
+pub type EnvString = alloc::string::String;
+
+/// The borrowed string type used for environment variable keys and values.
+/// See [`EnvString`] for its owned counterpart.
+#[cfg(feature = "std")]
+pub type EnvStr = std::ffi::OsStr;
+/// The borrowed string type used for environment variable keys and values.
+/// See [`EnvString`] for its owned counterpart.
+#[cfg(not(feature = "std"))]
+pub type EnvStr = str;
+
+#[cfg(feature = "std")]
+pub(crate) use std::collections::btree_map::IntoIter as EnvMapIntoIter;
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::btree_map::IntoIter as EnvMapIntoIter;
+
+// `ENV_MAP`'s backend is chosen by feature flag: the `rwlock` feature swaps
+// the exclusive `Mutex` for a true reader/writer lock, `parking_lot` swaps
+// either backend's `std::sync` implementation for its `parking_lot`
+// counterpart, and disabling `std` falls back to a `spin::Mutex`, which
+// requires the `spin` feature to be enabled separately (see the
+// `compile_error!` below for the case where neither is available).
+// See [`crate::UniversalLock`] for how read/write access is abstracted over
+// the result.
+#[cfg(all(feature = "std", feature = "rwlock", feature = "parking_lot"))]
+use parking_lot::RwLock as Lock;
+#[cfg(all(feature = "std", feature = "rwlock", not(feature = "parking_lot")))]
+use std::sync::RwLock as Lock;
+
+#[cfg(all(feature = "std", not(feature = "rwlock"), feature = "parking_lot"))]
+use parking_lot::Mutex as Lock;
+#[cfg(all(
+    feature = "std",
+    not(feature = "rwlock"),
+    not(feature = "parking_lot")
+))]
+use std::sync::Mutex as Lock;
+
+#[cfg(all(not(feature = "std"), feature = "spin"))]
+use spin::Mutex as Lock;
+
+#[cfg(not(any(feature = "std", feature = "spin")))]
+compile_error!(
+    "safenv requires either the `std` feature or the `spin` feature to provide a lock backend"
+);
+
+pub(crate) type EnvMap = BTreeMap<EnvString, EnvString>;
+
+pub(crate) static ENV_MAP: Lock<EnvMap> = Lock::new(BTreeMap::new());
 
 /// The error type for operations interacting with environment variables.
 /// Possibly returned from [`env::var()`].
@@ -24,13 +81,15 @@ pub enum VarError {
     /// The specified environment variable was found, but it did not contain
     /// valid unicode data. The found data is returned as a payload of this
     /// variant.
-    NotUnicode(OsString),
+    #[cfg(feature = "std")]
+    NotUnicode(EnvString),
 }
 
 impl fmt::Display for VarError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match *self {
             VarError::NotPresent => write!(f, "environment variable not found"),
+            #[cfg(feature = "std")]
             VarError::NotUnicode(ref s) => {
                 write!(f, "environment variable was not valid unicode: {s:?}")
             }
@@ -38,6 +97,7 @@ impl fmt::Display for VarError {
     }
 }
 
+#[cfg(feature = "std")]
 impl Error for VarError {
     fn description(&self) -> &str {
         match *self {
@@ -54,11 +114,11 @@ impl Error for VarError {
 ///
 /// [`env::vars_os()`]: super::vars_os
 pub struct VarsOs {
-    pub(crate) inner: std::collections::btree_map::IntoIter<std::ffi::OsString, std::ffi::OsString>,
+    pub(crate) inner: EnvMapIntoIter<EnvString, EnvString>,
 }
 
 impl Iterator for VarsOs {
-    type Item = (OsString, OsString);
+    type Item = (EnvString, EnvString);
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().map(|(k, v)| (k.clone(), v.clone()))
@@ -69,6 +129,7 @@ impl Iterator for VarsOs {
     }
 }
 
+#[cfg(feature = "std")]
 #[derive(Debug)]
 /// An iterator over a snapshot of the environment variables of this process.
 ///
@@ -79,6 +140,7 @@ pub struct Vars {
     pub(crate) inner: VarsOs,
 }
 
+#[cfg(feature = "std")]
 impl Iterator for Vars {
     type Item = (String, String);
 
@@ -0,0 +1,88 @@
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::os::unix::ffi::OsStrExt;
+
+use super::set_var;
+
+/// Bootstraps the `safenv` environment from a raw, null-terminated `environ`
+/// array of C strings.
+///
+/// This is intended for FFI-hosted and embedded runtimes that initialize
+/// their environment from a raw `envp` at startup rather than through
+/// `std::env`, giving `safenv` a way to seed its store where [`inherit()`]
+/// (which relies on [`std::env::vars_os`]) is unavailable or empty.
+///
+/// Walks `envp` until a null pointer is reached, splitting each `KEY=VALUE`
+/// C string on its first `=` and inserting the decoded key/value pair.
+/// Entries with no `=` are skipped.
+///
+/// Currently only available on Unix-like targets, where the process
+/// environment is represented as narrow (`char`) C strings.
+///
+/// # Safety
+/// `envp` must be null, or point to a null-terminated array of pointers to
+/// valid, null-terminated C strings, as provided by the C `environ` global
+/// at process startup.
+///
+/// # Panics
+/// If the environment lock is poisoned, this function will panic.
+///
+/// [`inherit()`]: super::inherit
+pub unsafe fn init_from_raw(envp: *const *const c_char) {
+    if envp.is_null() {
+        return;
+    }
+
+    let mut i = 0;
+    loop {
+        let entry = *envp.add(i);
+        if entry.is_null() {
+            break;
+        }
+
+        let bytes = CStr::from_ptr(entry).to_bytes();
+        if let Some(eq) = bytes.iter().position(|&b| b == b'=') {
+            let key = std::ffi::OsStr::from_bytes(&bytes[..eq]);
+            let value = std::ffi::OsStr::from_bytes(&bytes[eq + 1..]);
+            set_var(key, value);
+        }
+
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CString;
+    use std::ptr;
+
+    use super::init_from_raw;
+    use crate::env;
+
+    #[test]
+    fn walks_until_null_and_skips_entries_without_equals() {
+        let entries = [
+            CString::new("RAW_TEST_KEY=raw_value").unwrap(),
+            CString::new("RAW_TEST_NO_EQUALS").unwrap(),
+            CString::new("RAW_TEST_OTHER=other_value").unwrap(),
+        ];
+
+        let mut envp: Vec<_> = entries.iter().map(|entry| entry.as_ptr()).collect();
+        envp.push(ptr::null());
+
+        unsafe {
+            init_from_raw(envp.as_ptr());
+        }
+
+        assert_eq!(env::var("RAW_TEST_KEY"), Ok("raw_value".to_string()));
+        assert_eq!(env::var("RAW_TEST_OTHER"), Ok("other_value".to_string()));
+        assert!(env::var("RAW_TEST_NO_EQUALS").is_err());
+    }
+
+    #[test]
+    fn null_envp_is_a_no_op() {
+        unsafe {
+            init_from_raw(ptr::null());
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::UniversalLock;
+
+use super::{cwd, ENV_MAP};
+
+struct ScopeFrame {
+    seen: HashSet<OsString>,
+    deltas: Vec<(OsString, Option<OsString>)>,
+    cwd: Option<PathBuf>,
+}
+
+impl ScopeFrame {
+    fn new() -> Self {
+        Self {
+            seen: HashSet::new(),
+            deltas: Vec::new(),
+            cwd: None,
+        }
+    }
+}
+
+thread_local! {
+    // Each thread gets its own stack of frames, so a `scope()` guard only
+    // ever records mutations made, and is only ever popped, by the thread
+    // that created it.
+    static SCOPE_STACK: RefCell<Vec<ScopeFrame>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Records the previous value of `key` against every active scope on the
+/// current thread that hasn't already recorded a change to it, so that
+/// [`EnvGuard::drop`] can restore it later.
+///
+/// This must be called with the value `key` held *before* the mutation that
+/// is being recorded.
+pub(crate) fn record_mutation(key: &OsStr, previous: Option<&OsStr>) {
+    SCOPE_STACK.with(|stack| {
+        for frame in stack.borrow_mut().iter_mut() {
+            if frame.seen.insert(key.to_owned()) {
+                frame
+                    .deltas
+                    .push((key.to_owned(), previous.map(OsStr::to_owned)));
+            }
+        }
+    });
+}
+
+/// Records the previous working directory against every active scope on the
+/// current thread that hasn't already recorded a change to it, so that
+/// [`EnvGuard::drop`] can restore it later.
+///
+/// This must be called with the working directory held *before* the
+/// mutation that is being recorded.
+pub(crate) fn record_cwd_mutation(previous: &Path) {
+    SCOPE_STACK.with(|stack| {
+        for frame in stack.borrow_mut().iter_mut() {
+            if frame.cwd.is_none() {
+                frame.cwd = Some(previous.to_path_buf());
+            }
+        }
+    });
+}
+
+/// Begins a scoped environment override.
+///
+/// Returns an [`EnvGuard`] that, while alive, tracks every `safenv`
+/// environment variable mutation made through [`set_var`]/[`remove_var`], as
+/// well as any change to the [`current_dir`](super::current_dir). When the
+/// guard is dropped, all tracked state is restored to the value it held
+/// when the scope began, undoing the changes made within it.
+///
+/// This is useful for tests, and for code that needs to run a block with a
+/// temporarily modified environment without permanently clobbering it.
+///
+/// Scopes are per-thread: each thread tracks its own stack of active
+/// guards, so guards created on different threads never interfere with
+/// each other. Within a single thread, scopes nest: an inner guard restores
+/// its changes before an outer guard restores its own.
+///
+/// # Panics
+/// This function takes no lock. Dropping the returned guard panics if the
+/// environment lock is poisoned.
+///
+/// # Examples
+///
+/// ```
+/// use safenv::env;
+///
+/// env::set_var("KEY", "OUTER");
+/// {
+///     let _guard = env::scope();
+///     env::set_var("KEY", "INNER");
+///     assert_eq!(env::var("KEY"), Ok("INNER".to_string()));
+/// }
+/// assert_eq!(env::var("KEY"), Ok("OUTER".to_string()));
+/// ```
+///
+/// [`set_var`]: super::set_var
+/// [`remove_var`]: super::remove_var
+pub fn scope() -> EnvGuard {
+    SCOPE_STACK.with(|stack| stack.borrow_mut().push(ScopeFrame::new()));
+    EnvGuard {
+        _not_send: PhantomData,
+    }
+}
+
+/// A transactional guard over the `safenv` environment, created by
+/// [`scope()`]. See its documentation for more.
+///
+/// This type is deliberately `!Send`: it restores the scope it was created
+/// on, which only makes sense on the thread that created it.
+#[derive(Debug)]
+#[must_use = "the environment is restored when the guard is dropped; binding it to `_` drops it immediately"]
+pub struct EnvGuard {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        let Some(frame) = SCOPE_STACK.with(|stack| stack.borrow_mut().pop()) else {
+            return;
+        };
+
+        let mut map = ENV_MAP.u_write().unwrap();
+        for (key, previous) in frame.deltas.into_iter().rev() {
+            match previous {
+                Some(value) => {
+                    map.insert(key, value);
+                }
+                None => {
+                    map.remove(&key);
+                }
+            }
+        }
+        drop(map);
+
+        if let Some(previous) = frame.cwd {
+            cwd::restore(previous);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::scope;
+    use crate::env;
+    use crate::env::{current_dir, set_current_dir};
+
+    #[test]
+    fn nested_scopes_restore_in_order() {
+        env::set_var("SCOPE_TEST_NESTED", "OUTER");
+        {
+            let _outer = scope();
+            env::set_var("SCOPE_TEST_NESTED", "MIDDLE");
+            {
+                let _inner = scope();
+                env::set_var("SCOPE_TEST_NESTED", "INNER");
+                assert_eq!(env::var("SCOPE_TEST_NESTED"), Ok("INNER".to_string()));
+            }
+            assert_eq!(env::var("SCOPE_TEST_NESTED"), Ok("MIDDLE".to_string()));
+        }
+        assert_eq!(env::var("SCOPE_TEST_NESTED"), Ok("OUTER".to_string()));
+    }
+
+    #[test]
+    fn scopes_do_not_cross_threads() {
+        env::set_var("SCOPE_TEST_THREADED", "BASE");
+
+        thread::spawn(|| {
+            let _guard = scope();
+            env::set_var("SCOPE_TEST_THREADED", "THREAD");
+            assert_eq!(env::var("SCOPE_TEST_THREADED"), Ok("THREAD".to_string()));
+            // The guard drops here, on this thread, restoring the value.
+        })
+        .join()
+        .unwrap();
+
+        // A guard created (and popped) on another thread must never touch
+        // this thread's scope stack, and the value it restored must be
+        // visible here too since `ENV_MAP` itself is process-wide.
+        assert_eq!(env::var("SCOPE_TEST_THREADED"), Ok("BASE".to_string()));
+    }
+
+    #[test]
+    fn scope_restores_the_working_directory_on_drop() {
+        let before = current_dir().unwrap();
+        {
+            let _guard = scope();
+            set_current_dir("/").unwrap();
+            assert_eq!(current_dir().unwrap(), std::path::Path::new("/"));
+        }
+        assert_eq!(current_dir().unwrap(), before);
+    }
+}
@@ -1,63 +1,238 @@
 #![doc = include_str!("../README.md")]
 #![warn(clippy::all, clippy::pedantic, missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use core::ops::DerefMut;
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+use core::ops::{Deref, DerefMut};
 
 pub mod env;
 
 pub use env::*;
 
+/// Abstracts over lock backends that distinguish shared (read) access from
+/// exclusive (write) access, so callers on the read path don't serialize
+/// behind callers that only ever read too.
+///
+/// Single-mode locks (like [`std::sync::Mutex`]) implement this by routing
+/// both `u_read` and `u_write` through their one exclusive lock; true
+/// reader/writer locks (like [`std::sync::RwLock`]) give `u_read` a shared
+/// guard.
 trait UniversalLock {
     type Target;
-    type Lock<'a>: DerefMut<Target = Self::Target>
+    type ReadGuard<'a>: Deref<Target = Self::Target>
+    where
+        Self::Target: 'a,
+        Self: 'a;
+    type WriteGuard<'a>: DerefMut<Target = Self::Target>
     where
         Self::Target: 'a,
         Self: 'a;
 
-    type InfallibleError<'a>
+    type ReadError<'a>
     where
         Self::Target: 'a,
         Self: 'a;
-    type FallibleError<'a>
+    type WriteError<'a>
     where
         Self::Target: 'a,
         Self: 'a;
+    type TryWriteError<'a>
+    where
+        Self::Target: 'a,
+        Self: 'a;
+
+    fn u_read(&self) -> Result<Self::ReadGuard<'_>, Self::ReadError<'_>>;
 
-    fn u_lock(&self) -> Result<Self::Lock<'_>, Self::InfallibleError<'_>>;
+    fn u_write(&self) -> Result<Self::WriteGuard<'_>, Self::WriteError<'_>>;
 
     #[allow(dead_code)]
-    fn u_try_lock(&self) -> Result<Self::Lock<'_>, Self::FallibleError<'_>>;
+    fn u_try_write(&self) -> Result<Self::WriteGuard<'_>, Self::TryWriteError<'_>>;
 }
 
+#[cfg(feature = "std")]
 impl<T> UniversalLock for std::sync::Mutex<T> {
     type Target = T;
-    type Lock<'a> = std::sync::MutexGuard<'a, T> where T: 'a;
+    type ReadGuard<'a> = std::sync::MutexGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = std::sync::MutexGuard<'a, T> where T: 'a;
 
-    type InfallibleError<'a> = std::sync::PoisonError<Self::Lock<'a>> where T: 'a;
-    type FallibleError<'a> = std::sync::TryLockError<Self::Lock<'a>> where T: 'a;
+    type ReadError<'a> = std::sync::PoisonError<Self::WriteGuard<'a>> where T: 'a;
+    type WriteError<'a> = std::sync::PoisonError<Self::WriteGuard<'a>> where T: 'a;
+    type TryWriteError<'a> = std::sync::TryLockError<Self::WriteGuard<'a>> where T: 'a;
 
-    fn u_lock(&self) -> Result<Self::Lock<'_>, Self::InfallibleError<'_>> {
+    fn u_read(&self) -> Result<Self::ReadGuard<'_>, Self::ReadError<'_>> {
         std::sync::Mutex::lock(self)
     }
 
-    fn u_try_lock(&self) -> Result<Self::Lock<'_>, Self::FallibleError<'_>> {
+    fn u_write(&self) -> Result<Self::WriteGuard<'_>, Self::WriteError<'_>> {
+        std::sync::Mutex::lock(self)
+    }
+
+    fn u_try_write(&self) -> Result<Self::WriteGuard<'_>, Self::TryWriteError<'_>> {
         std::sync::Mutex::try_lock(self)
     }
 }
 
-#[cfg(feature = "parking_lot")]
+#[cfg(all(feature = "std", feature = "parking_lot"))]
 impl<T> UniversalLock for parking_lot::Mutex<T> {
     type Target = T;
-    type Lock<'a> = parking_lot::MutexGuard<'a, T> where T: 'a;
+    type ReadGuard<'a> = parking_lot::MutexGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = parking_lot::MutexGuard<'a, T> where T: 'a;
 
-    type InfallibleError<'a> = () where T: 'a;
-    type FallibleError<'a> = () where T: 'a;
+    type ReadError<'a> = () where T: 'a;
+    type WriteError<'a> = () where T: 'a;
+    type TryWriteError<'a> = () where T: 'a;
 
-    fn u_lock(&self) -> Result<Self::Lock<'_>, Self::InfallibleError<'_>> {
+    fn u_read(&self) -> Result<Self::ReadGuard<'_>, Self::ReadError<'_>> {
         Ok(parking_lot::Mutex::lock(self))
     }
 
-    fn u_try_lock(&self) -> Result<Self::Lock<'_>, Self::FallibleError<'_>> {
+    fn u_write(&self) -> Result<Self::WriteGuard<'_>, Self::WriteError<'_>> {
+        Ok(parking_lot::Mutex::lock(self))
+    }
+
+    fn u_try_write(&self) -> Result<Self::WriteGuard<'_>, Self::TryWriteError<'_>> {
         parking_lot::Mutex::try_lock(self).ok_or(())
     }
 }
+
+#[cfg(feature = "std")]
+impl<T> UniversalLock for std::sync::RwLock<T> {
+    type Target = T;
+    type ReadGuard<'a> = std::sync::RwLockReadGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = std::sync::RwLockWriteGuard<'a, T> where T: 'a;
+
+    type ReadError<'a> = std::sync::PoisonError<Self::ReadGuard<'a>> where T: 'a;
+    type WriteError<'a> = std::sync::PoisonError<Self::WriteGuard<'a>> where T: 'a;
+    type TryWriteError<'a> = std::sync::TryLockError<Self::WriteGuard<'a>> where T: 'a;
+
+    fn u_read(&self) -> Result<Self::ReadGuard<'_>, Self::ReadError<'_>> {
+        std::sync::RwLock::read(self)
+    }
+
+    fn u_write(&self) -> Result<Self::WriteGuard<'_>, Self::WriteError<'_>> {
+        std::sync::RwLock::write(self)
+    }
+
+    fn u_try_write(&self) -> Result<Self::WriteGuard<'_>, Self::TryWriteError<'_>> {
+        std::sync::RwLock::try_write(self)
+    }
+}
+
+#[cfg(all(feature = "std", feature = "parking_lot"))]
+impl<T> UniversalLock for parking_lot::RwLock<T> {
+    type Target = T;
+    type ReadGuard<'a> = parking_lot::RwLockReadGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = parking_lot::RwLockWriteGuard<'a, T> where T: 'a;
+
+    type ReadError<'a> = () where T: 'a;
+    type WriteError<'a> = () where T: 'a;
+    type TryWriteError<'a> = () where T: 'a;
+
+    fn u_read(&self) -> Result<Self::ReadGuard<'_>, Self::ReadError<'_>> {
+        Ok(parking_lot::RwLock::read(self))
+    }
+
+    fn u_write(&self) -> Result<Self::WriteGuard<'_>, Self::WriteError<'_>> {
+        Ok(parking_lot::RwLock::write(self))
+    }
+
+    fn u_try_write(&self) -> Result<Self::WriteGuard<'_>, Self::TryWriteError<'_>> {
+        parking_lot::RwLock::try_write(self).ok_or(())
+    }
+}
+
+/// A spinlock backend for hosts without `std`, such as SGX enclaves and
+/// unikernel/hypervisor targets. Enabled by the `spin` feature, which is
+/// required when the `std` feature is disabled since no other backend here
+/// is available without it.
+#[cfg(feature = "spin")]
+impl<T> UniversalLock for spin::Mutex<T> {
+    type Target = T;
+    type ReadGuard<'a> = spin::MutexGuard<'a, T> where T: 'a;
+    type WriteGuard<'a> = spin::MutexGuard<'a, T> where T: 'a;
+
+    type ReadError<'a> = () where T: 'a;
+    type WriteError<'a> = () where T: 'a;
+    type TryWriteError<'a> = () where T: 'a;
+
+    fn u_read(&self) -> Result<Self::ReadGuard<'_>, Self::ReadError<'_>> {
+        Ok(spin::Mutex::lock(self))
+    }
+
+    fn u_write(&self) -> Result<Self::WriteGuard<'_>, Self::WriteError<'_>> {
+        Ok(spin::Mutex::lock(self))
+    }
+
+    fn u_try_write(&self) -> Result<Self::WriteGuard<'_>, Self::TryWriteError<'_>> {
+        spin::Mutex::try_lock(self).ok_or(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::UniversalLock;
+
+    #[test]
+    fn mutex_write_is_visible_to_read() {
+        let lock = std::sync::Mutex::new(0);
+        *lock.u_write().unwrap() += 1;
+        assert_eq!(*lock.u_read().unwrap(), 1);
+    }
+
+    #[test]
+    fn rwlock_write_is_visible_to_read() {
+        let lock = std::sync::RwLock::new(0);
+        *lock.u_write().unwrap() += 1;
+        assert_eq!(*lock.u_read().unwrap(), 1);
+    }
+
+    #[test]
+    fn rwlock_try_write_succeeds_when_uncontended() {
+        let lock = std::sync::RwLock::new(0);
+        *lock.u_try_write().unwrap() += 1;
+        assert_eq!(*lock.u_read().unwrap(), 1);
+    }
+
+    #[cfg(feature = "parking_lot")]
+    #[test]
+    fn parking_lot_mutex_write_is_visible_to_read() {
+        let lock = parking_lot::Mutex::new(0);
+        *lock.u_write().unwrap() += 1;
+        assert_eq!(*lock.u_read().unwrap(), 1);
+    }
+
+    #[cfg(feature = "parking_lot")]
+    #[test]
+    fn parking_lot_rwlock_write_is_visible_to_read() {
+        let lock = parking_lot::RwLock::new(0);
+        *lock.u_write().unwrap() += 1;
+        assert_eq!(*lock.u_read().unwrap(), 1);
+    }
+
+    #[cfg(feature = "parking_lot")]
+    #[test]
+    fn parking_lot_rwlock_try_write_succeeds_when_uncontended() {
+        let lock = parking_lot::RwLock::new(0);
+        *lock.u_try_write().unwrap() += 1;
+        assert_eq!(*lock.u_read().unwrap(), 1);
+    }
+
+    // Drives the real `ENV_MAP`/public API through whichever backend this
+    // build was compiled with (plain `Mutex`, `RwLock`, or a `parking_lot`
+    // variant of either via the `rwlock`/`parking_lot` features), rather than
+    // just exercising `UniversalLock` against a freshly-constructed lock.
+    #[test]
+    fn env_map_round_trips_through_the_selected_backend() {
+        crate::env::set_var("LIB_TEST_BACKEND_KEY", "BACKEND_VALUE");
+        assert_eq!(
+            crate::env::var("LIB_TEST_BACKEND_KEY"),
+            Ok("BACKEND_VALUE".to_string())
+        );
+        assert!(crate::env::vars_os().any(|(k, v)| {
+            k == std::ffi::OsStr::new("LIB_TEST_BACKEND_KEY")
+                && v == std::ffi::OsStr::new("BACKEND_VALUE")
+        }));
+    }
+}